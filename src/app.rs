@@ -4,25 +4,55 @@
  */
 
 use std::future::Future;
+use std::rc::Rc;
+use std::cell::{ Cell, RefCell };
+use std::fs::create_dir_all;
 use async_channel::{ unbounded, Sender };
+use std::collections::HashMap;
 use gtk::{
     main_quit, Inhibit, init, main,
-    Button, Box, Orientation, TextView, Grid, TextBuffer, Label,
-    Menu, MenuItem, MenuButton,
-    Window, WindowType, Align, Dialog, DialogFlags, ResponseType,
+    Button, Box, Orientation, TextView, Grid, TextBuffer, Label, ProgressBar,
+    Menu, MenuItem, MenuButton, Notebook, SearchEntry, Popover, Image, Entry,
+    Window, WindowType, Align, Dialog, DialogFlags, ResponseType, IconSize,
+    Clipboard, gdk_pixbuf::Pixbuf,
     prelude::{
         ContainerExt, ButtonExt, BoxExt, WidgetExt, GtkWindowExt, GridExt,
-        TextBufferExt, MenuButtonExt, MenuShellExt, GtkMenuItemExt,
-        DialogExt
-    }, glib::{ set_program_name, set_application_name, MainContext }
+        TextBufferExt, MenuButtonExt, MenuShellExt, GtkMenuItemExt, GtkMenuExt,
+        DialogExt, NotebookExt, EntryExt, PopoverExt, ProgressBarExt,
+        ImageExt, ClipboardExt
+    }, glib::{ set_program_name, set_application_name, MainContext, Cast }
+};
+use gdk::SELECTION_CLIPBOARD;
+use webkit2gtk::{
+    WebView, LoadEvent, WebContext, URIRequest, PolicyDecisionType,
+    NavigationPolicyDecision,
+    traits::{
+        WebViewExt, WebContextExt, DownloadExt, URIRequestExt,
+        URIResponseExt, HitTestResultExt, PolicyDecisionExt,
+        NavigationPolicyDecisionExt
+    }
 };
-use webkit2gtk::{ WebView, LoadEvent, traits::WebViewExt };
 use serde::{ Serialize, Deserialize };
 use log::{ warn, error, info };
 use confy::{ load, store };
 use cascade::cascade;
 use home::home_dir;
 
+mod history;
+use history::History;
+mod crypto;
+mod downloads;
+use downloads::Downloads;
+mod favicon;
+use favicon::{ FaviconCache, host_of };
+mod blocklist;
+use blocklist::Blocklist;
+
+// Schemes a navigation is allowed to go to; navigations to any other scheme
+// are refused
+const TRUSTED_SCHEMES: &'static [&'static str] =
+    &[ "http", "https", "file", "about", "data" ];
+
 const WIN_TITLE: &'static str = "Browse the Web";
 const WIN_DEF_WIDTH: i32 = 640;
 const WIND_DEF_HEIGHT: i32 = 480;
@@ -33,6 +63,354 @@ fn spawn<F>(future: F) where F: Future<Output = ()> + 'static {
     MainContext::default().spawn_local(future);
 }
 
+// Builds a WebView whose load signals are tagged with the owning tab's
+// current position. `tab_id` is shared with the Tab this view belongs to
+// and updated in place whenever a lower-numbered tab closes, so a closure
+// created at position 2 still tags events with the right position after
+// tabs shift underneath it (see EventType::CloseTab)
+fn build_web_view(
+    tx: Sender<Event>, tab_id: Rc<Cell<usize>>, start_url: &str,
+    blocklist: Rc<RefCell<Vec<String>>>
+) -> WebView {
+    let web_tx1 = tx.clone();
+    let web_tx2 = tx.clone();
+    let tx_ctx_menu = tx.clone();
+    let res_tx = tx.clone();
+    let id1 = tab_id.clone();
+    let id2 = tab_id.clone();
+    let id3 = tab_id.clone();
+    cascade! {
+        WebView::builder().build();
+            ..load_uri(start_url);
+            ..connect_load_changed(move |view, load_ev| {
+                if load_ev == LoadEvent::Started {
+                    let tx = web_tx1.clone();
+                    let txt = WebView::uri(&view).unwrap().to_string();
+                    let tab = id1.get();
+                    spawn(async move {
+                        let _ = tx.send(Event {
+                            tp: EventType::ChangedPage, url: txt, tab
+                        }).await;
+                    });
+                }
+            });
+            ..connect_load_failed(move |_, _, uri, _| {
+                let tx = web_tx2.clone();
+                let url = String::from(uri);
+                let tab = id2.get();
+                spawn(async move {
+                    let _ = tx.send(Event {
+                        tp: EventType::FailedChangePage, url, tab
+                    }).await;
+                });
+                true
+            });
+            ..connect_resource_load_started(move |_, _resource, request| {
+                if let Some(uri) = request.uri() {
+                    if let Some(host) = host_of(&uri) {
+                        // Checked synchronously against the shared cache so
+                        // we can redirect the request before it goes out;
+                        // a round trip through the event channel would be
+                        // too late, the request object only lives this long
+                        let blocked = blocklist.borrow().iter()
+                            .any(|pattern| blocklist::matches(pattern, &host));
+                        if blocked {
+                            request.set_uri("about:blank");
+                        }
+
+                        let tx = res_tx.clone();
+                        let tab = id3.get();
+                        spawn(async move {
+                            let _ = tx.send(Event {
+                                tp: EventType::ResourceSeen(host, blocked),
+                                url: String::new(), tab
+                            }).await;
+                        });
+                    }
+                }
+            });
+            ..connect_decide_policy(move |_, decision, decision_type| {
+                if decision_type != PolicyDecisionType::NavigationAction {
+                    return false;
+                }
+
+                let nav_decision = match decision
+                    .downcast_ref::<NavigationPolicyDecision>()
+                {
+                    Some(nav_decision) => nav_decision,
+                    None => return false
+                };
+                let uri = match nav_decision.request()
+                    .and_then(|req| req.uri())
+                {
+                    Some(uri) => uri,
+                    None => return false
+                };
+
+                let scheme = uri.split(':').next().unwrap_or("");
+                if !TRUSTED_SCHEMES.contains(&scheme) {
+                    warn!("Refusing navigation to untrusted scheme: {}.", scheme);
+                    decision.ignore();
+                    return true;
+                }
+
+                false
+            });
+            ..connect_context_menu(move |view, _default_menu, event, hit| {
+                let menu = Menu::builder().build();
+
+                if hit.context_is_link() {
+                    let link_url = hit.link_uri()
+                        .map(|u| u.to_string()).unwrap_or_default();
+
+                    let open_tx = tx_ctx_menu.clone();
+                    let open_url = link_url.clone();
+                    let open_item = cascade! {
+                        MenuItem::with_label("Open Link in New Tab");
+                            ..connect_activate(move |_| {
+                                let tx = open_tx.clone();
+                                let url = open_url.clone();
+                                spawn(async move {
+                                    let _ = tx.send(Event {
+                                        tp: EventType::NewTab, url, tab: 0
+                                    }).await;
+                                });
+                            });
+                    };
+                    menu.append(&open_item);
+
+                    let copy_url = link_url.clone();
+                    let copy_item = cascade! {
+                        MenuItem::with_label("Copy Link Address");
+                            ..connect_activate(move |_| {
+                                Clipboard::get(&SELECTION_CLIPBOARD)
+                                    .set_text(&copy_url);
+                            });
+                    };
+                    menu.append(&copy_item);
+
+                    let bookmark_tx = tx_ctx_menu.clone();
+                    let bookmark_url = link_url.clone();
+                    let bookmark_item = cascade! {
+                        MenuItem::with_label("Bookmark This Link");
+                            ..connect_activate(move |_| {
+                                let tx = bookmark_tx.clone();
+                                let url = bookmark_url.clone();
+                                spawn(async move {
+                                    let _ = tx.send(Event {
+                                        tp: EventType::AddBookmark(url),
+                                        url: String::new(), tab: 0
+                                    }).await;
+                                });
+                            });
+                    };
+                    menu.append(&bookmark_item);
+                } else {
+                    let back_tx = tx_ctx_menu.clone();
+                    let back_item = cascade! {
+                        MenuItem::with_label("Back");
+                            ..connect_activate(move |_| {
+                                let tx = back_tx.clone();
+                                spawn(async move {
+                                    let _ = tx.send(Event {
+                                        tp: EventType::BackClicked,
+                                        url: String::new(), tab: 0
+                                    }).await;
+                                });
+                            });
+                    };
+                    menu.append(&back_item);
+
+                    let fwd_tx = tx_ctx_menu.clone();
+                    let fwd_item = cascade! {
+                        MenuItem::with_label("Forward");
+                            ..connect_activate(move |_| {
+                                let tx = fwd_tx.clone();
+                                spawn(async move {
+                                    let _ = tx.send(Event {
+                                        tp: EventType::ForwardClicked,
+                                        url: String::new(), tab: 0
+                                    }).await;
+                                });
+                            });
+                    };
+                    menu.append(&fwd_item);
+
+                    let reload_tx = tx_ctx_menu.clone();
+                    let reload_item = cascade! {
+                        MenuItem::with_label("Reload");
+                            ..connect_activate(move |_| {
+                                let tx = reload_tx.clone();
+                                spawn(async move {
+                                    let _ = tx.send(Event {
+                                        tp: EventType::RefreshClicked,
+                                        url: String::new(), tab: 0
+                                    }).await;
+                                });
+                            });
+                    };
+                    menu.append(&reload_item);
+
+                    let bookmark_tx = tx_ctx_menu.clone();
+                    let page_url = WebView::uri(&view)
+                        .map(|u| u.to_string()).unwrap_or_default();
+                    let bookmark_item = cascade! {
+                        MenuItem::with_label("Add Current Page to Bookmarks");
+                            ..connect_activate(move |_| {
+                                let tx = bookmark_tx.clone();
+                                let url = page_url.clone();
+                                spawn(async move {
+                                    let _ = tx.send(Event {
+                                        tp: EventType::AddBookmark(url),
+                                        url: String::new(), tab: 0
+                                    }).await;
+                                });
+                            });
+                    };
+                    menu.append(&bookmark_item);
+                }
+
+                menu.show_all();
+                menu.popup_at_pointer(Some(event));
+
+                // We've shown our own menu; suppress WebKit's default one
+                true
+            });
+    }
+}
+
+// Small "title + close button" widget shown on the Notebook tab strip.
+// `tab_id` is the same shared cell handed to this tab's WebView, so the
+// close button always targets the tab's current position, not the one it
+// was created at
+fn build_tab_label(tx: Sender<Event>, tab_id: Rc<Cell<usize>>) -> Box {
+    let close_tx = tx.clone();
+    cascade! {
+        Box::new(Orientation::Horizontal, 4);
+            ..pack_start(&Label::new(Some("New Tab")), true, true, 0);
+            ..pack_start(
+                &cascade! {
+                    Button::with_label("×");
+                        ..connect_clicked(move |_| {
+                            let tx = close_tx.clone();
+                            let tab = tab_id.get();
+                            spawn(async move {
+                                let _ = tx.send(Event {
+                                    tp: EventType::CloseTab,
+                                    url: String::new(),
+                                    tab
+                                }).await;
+                            });
+                        });
+                },
+                false, false, 0
+            );
+            ..show_all();
+    }
+}
+
+// A bookmark row, icon-first: the icon defaults to a generic page glyph and
+// is swapped for the real favicon when a page on its host loads with an icon
+// cached, or when that host's icon is fetched
+fn build_bookmark_item(name: &str) -> (MenuItem, Image) {
+    let icon = Image::from_icon_name(Some("text-html"), IconSize::Menu);
+    let row = cascade! {
+        Box::new(Orientation::Horizontal, 4);
+            ..pack_start(&icon, false, false, 0);
+            ..pack_start(&Label::new(Some(name)), true, true, 0);
+    };
+    let item = MenuItem::builder().build();
+    item.add(&row);
+    (item, icon)
+}
+
+// Resolves a page's favicon (its <link rel="icon">, falling back to
+// /favicon.ico) on a background thread and reports the raw bytes back
+fn fetch_favicon(tx: Sender<Event>, web_view: WebView, host: String) {
+    let fallback = format!("https://{}/favicon.ico", host);
+    web_view.run_javascript(
+        "(function() { \
+            var l = document.querySelector(\"link[rel~='icon']\"); \
+            return l ? l.href : ''; \
+        })()",
+        gio::Cancellable::NONE,
+        move |res| {
+            let href = res.ok()
+                .and_then(|js| js.js_value())
+                .and_then(|val| val.to_string())
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| fallback.clone());
+
+            let tx = tx.clone();
+            let host = host.clone();
+            std::thread::spawn(move || {
+                match ureq::get(&href).call() {
+                    Err(_) => warn!("Failed to fetch favicon for {}.", host),
+                    Ok(resp) => {
+                        let mut bytes = Vec::new();
+                        if std::io::Read::read_to_end(
+                            &mut resp.into_reader(), &mut bytes
+                        ).is_ok() {
+                            // We're off the GTK main thread here, so this
+                            // has to be a blocking send rather than spawn()
+                            let _ = tx.send_blocking(Event {
+                                tp: EventType::FaviconDownloaded(host, bytes),
+                                url: String::new(), tab: 0
+                            });
+                        }
+                    }
+                }
+            });
+        }
+    );
+}
+
+// Pulls this account's still-encrypted bookmarks blob from the sync
+// server on a background thread (network calls can't run on the GTK main
+// thread) and reports back whatever it finds, so SyncRemoteFetched can
+// decrypt and merge it in
+fn fetch_synced_bookmarks(
+    tx: Sender<Event>, server: String, username: String, password: String
+) {
+    std::thread::spawn(move || {
+        let url = format!("{}/bookmarks/{}", server.trim_end_matches('/'), username);
+        let remote = match ureq::get(&url).call() {
+            Err(_) => None,
+            Ok(resp) => {
+                let mut bytes = Vec::new();
+                if std::io::Read::read_to_end(
+                    &mut resp.into_reader(), &mut bytes
+                ).is_ok() {
+                    Some(bytes)
+                } else {
+                    None
+                }
+            }
+        };
+
+        // We're off the GTK main thread here, so this has to be a
+        // blocking send rather than spawn()
+        let _ = tx.send_blocking(Event {
+            tp: EventType::SyncRemoteFetched(username, password, remote),
+            url: String::new(), tab: 0
+        });
+    });
+}
+
+// Pushes the newly-merged, still-encrypted bookmarks blob up to the sync
+// server so other installs signed into the same account can pull it down.
+// Fire-and-forget: the local save already happened, so there's no UI
+// state waiting on this succeeding
+fn upload_synced_bookmarks(server: String, username: String, blob: String) {
+    std::thread::spawn(move || {
+        let url = format!("{}/bookmarks/{}", server.trim_end_matches('/'), username);
+        match ureq::put(&url).send_string(&blob) {
+            Err(_) => warn!("Failed to upload synced bookmarks for {}.", username),
+            Ok(_) => info!("Uploaded synced bookmarks for {}.", username)
+        }
+    });
+}
+
 pub fn start_browser() {
     set_program_name(APP_NAME.into());
     set_application_name(APP_NAME);
@@ -45,77 +423,187 @@ pub fn start_browser() {
 
     // Attach tx to widgets and rx to handler
     let (tx, rx) = unbounded();
-    let app = AppState::new(tx);
+    let mut app = AppState::new(tx.clone());
 
-    let mut via_nav_btns = false;
-    let mut back_urls = vec![ app.cfg.start_page ];
-    let mut fwd_urls = Vec::new();
-
-    let mut err_url = String::new();
+    let mut active_tab: usize = 0;
 
     let event_handler = async move {
         while let Ok(event) = rx.recv().await {
             match event.tp {
                 EventType::BackClicked => {
-                    if back_urls.len() > 1 {
-                        fwd_urls.push(back_urls.pop());
+                    let tab = &mut app.tabs[active_tab];
+                    if tab.back_urls.len() > 1 {
+                        tab.fwd_urls.push(tab.back_urls.pop());
 
-                        via_nav_btns = true;
-                        app.web_view.load_uri(
-                            back_urls[back_urls.len() - 1].as_str()
-                        );
+                        tab.via_nav_btns = true;
+                        let url = tab.back_urls[tab.back_urls.len() - 1].clone();
+                        tab.web_view.load_uri(url.as_str());
 
-                        info!("Back to {}.", back_urls[back_urls.len() - 1]);
+                        info!("Back to {}.", url);
 
-                        app.tb_buff.set_text(
-                            back_urls[back_urls.len() - 1].as_str()
-                        );
+                        app.tb_buff.set_text(url.as_str());
                     }
                 }, EventType::ForwardClicked => {
-                    if fwd_urls.len() > 0 {
-                        back_urls.push(fwd_urls[0].clone().unwrap());
-                        fwd_urls.remove(0);
+                    let tab = &mut app.tabs[active_tab];
+                    if tab.fwd_urls.len() > 0 {
+                        tab.back_urls.push(tab.fwd_urls[0].clone().unwrap());
+                        tab.fwd_urls.remove(0);
 
-                        via_nav_btns = true;
-                        app.web_view.load_uri(
-                            back_urls[back_urls.len() - 1].as_str()
-                        );
+                        tab.via_nav_btns = true;
+                        let url = tab.back_urls[tab.back_urls.len() - 1].clone();
+                        tab.web_view.load_uri(url.as_str());
 
-                        info!("Forward to {}.", back_urls[back_urls.len() - 1]);
+                        info!("Forward to {}.", url);
 
-                        app.tb_buff.set_text(
-                            back_urls[back_urls.len() - 1].as_str()
-                        );
+                        app.tb_buff.set_text(url.as_str());
                     }
                 }, EventType::RefreshClicked => {
-                    via_nav_btns = true;
-                    app.web_view.reload();
+                    let tab = &mut app.tabs[active_tab];
+                    tab.via_nav_btns = true;
+                    tab.web_view.reload();
                 }, EventType::ChangedPage => {
+                    let tab = &mut app.tabs[event.tab];
+
+                    tab.blocked_count = 0;
+                    tab.seen_hosts = Vec::new();
+
+                    if event.tab == active_tab {
+                        app.blocklist_btn.set_label("⛔ 0");
+                    }
+
                     // Don't re-navigate after pressing back
-                    if via_nav_btns {
-                        via_nav_btns = false;
+                    if tab.via_nav_btns {
+                        tab.via_nav_btns = false;
                         continue;
                     }
 
-                    info!("Changed page to {}.", event.url);
+                    info!("Changed page to {} (tab {}).", event.url, event.tab);
+
+                    tab.fwd_urls = Vec::new();
+                    tab.back_urls.push(event.url.clone());
+
+                    let title = tab.web_view.title()
+                        .map(|t| t.to_string()).unwrap_or_default();
+                    app.history.record(APP_NAME, event.url.clone(), title);
 
-                    fwd_urls = Vec::new();
-                    back_urls.push(event.url.clone());
+                    if let Some(host) = host_of(&event.url) {
+                        if let Some(path) = app.favicons.cached_path(&host) {
+                            if event.tab == active_tab {
+                                if let Ok(pixbuf) = Pixbuf::from_file(&path) {
+                                    app.win.set_icon(Some(&pixbuf));
+                                }
+                            }
+                            if let Some(icon) = app.bookmark_icons.get(&host) {
+                                icon.set_from_file(Some(&path));
+                            }
+                        }
+
+                        if app.favicons.needs_refresh(&host) {
+                            fetch_favicon(
+                                tx.clone(),
+                                app.tabs[event.tab].web_view.clone(),
+                                host
+                            );
+                        }
+                    }
 
-                    app.tb_buff.set_text(event.url.as_str());
+                    if event.tab == active_tab {
+                        app.tb_buff.set_text(event.url.as_str());
+                    }
                 }, EventType::ChangePage => {
-                    app.web_view.load_uri(&event.url);
+                    app.tabs[active_tab].web_view.load_uri(&event.url);
                 }, EventType::FailedChangePage => {
-                    if event.url == err_url {
+                    let tab = &mut app.tabs[event.tab];
+                    if event.url == tab.err_url {
                         let home_dir =
                             home_dir().unwrap().display().to_string();
-                        app.web_view.load_uri(
+                        tab.web_view.load_uri(
                             (String::from("file://") + &home_dir).as_str()
                         );
                     } else {
-                        err_url =
+                        tab.err_url =
                             app.cfg.search_engine.replace("${}", &event.url);
-                        app.web_view.load_uri(err_url.as_str());
+                        let err_url = tab.err_url.clone();
+                        tab.web_view.load_uri(err_url.as_str());
+                    }
+                }, EventType::NewTab => {
+                    let idx = app.tabs.len();
+                    let tab_id = Rc::new(Cell::new(idx));
+
+                    // A context-menu "Open Link in New Tab" sends the link's
+                    // URL here; anything else (the + button) leaves it empty
+                    let target_url = if event.url.is_empty() {
+                        app.cfg.start_page.clone()
+                    } else {
+                        event.url.clone()
+                    };
+
+                    let web_view = build_web_view(
+                        tx.clone(), tab_id.clone(), &target_url,
+                        app.blocklist_shared.clone()
+                    );
+                    let web_box = cascade! {
+                        Box::new(Orientation::Horizontal, 0);
+                            ..pack_start(
+                                &web_view, true, true, app.cfg.margin
+                            );
+                    };
+                    web_box.show_all();
+
+                    app.notebook.append_page(
+                        &web_box,
+                        Some(&build_tab_label(tx.clone(), tab_id.clone()))
+                    );
+                    app.notebook.set_tab_reorderable(&web_box, true);
+
+                    app.tabs.push(Tab {
+                        web_view,
+                        back_urls: vec![ target_url ],
+                        fwd_urls: Vec::new(),
+                        via_nav_btns: false,
+                        err_url: String::new(),
+                        blocked_count: 0,
+                        seen_hosts: Vec::new(),
+                        id: tab_id
+                    });
+
+                    app.notebook.set_current_page(Some(idx as u32));
+                }, EventType::CloseTab => {
+                    let idx = event.tab;
+
+                    if app.tabs.len() <= 1 {
+                        // Always keep at least one tab open, reset it instead
+                        let tab = &mut app.tabs[0];
+                        tab.back_urls = vec![ app.cfg.start_page.clone() ];
+                        tab.fwd_urls = Vec::new();
+                        tab.web_view.load_uri(&app.cfg.start_page);
+                        continue;
+                    }
+
+                    app.notebook.remove_page(Some(idx as u32));
+                    app.tabs.remove(idx);
+
+                    // Every tab after the removed one just shifted down one
+                    // vector position; update its shared id so its already-
+                    // built WebView/label closures keep tagging events with
+                    // the right position instead of the stale one they were
+                    // created with
+                    for (i, tab) in app.tabs.iter().enumerate().skip(idx) {
+                        tab.id.set(i);
+                    }
+
+                    if active_tab >= app.tabs.len() {
+                        active_tab = app.tabs.len() - 1;
+                    }
+                }, EventType::SwitchTab(idx) => {
+                    if idx < app.tabs.len() {
+                        active_tab = idx;
+                        let tab = &app.tabs[idx];
+                        let url = tab.back_urls[tab.back_urls.len() - 1].clone();
+                        app.tb_buff.set_text(url.as_str());
+                        app.blocklist_btn.set_label(
+                            &format!("⛔ {}", tab.blocked_count)
+                        );
                     }
                 }, EventType::LoginRegister => {
                     /* Create a login prompt */
@@ -125,13 +613,11 @@ pub fn start_browser() {
                             DialogFlags::from_bits(1).unwrap(),
                             &[ ("_OK", ResponseType::Accept) ]
                         );
-                        ..connect_response(move |view, _| {
-                            view.hide();
-                        });
                     };
                     let content_area = dialog.content_area();
 
-                    let uname_buff = TextBuffer::builder().build();
+                    let uname_buff =
+                        TextBuffer::builder().text(&app.cfg.username).build();
                     let uname = cascade! {
                         Box::new(Orientation::Horizontal, 0);
                             ..pack_start(
@@ -144,10 +630,476 @@ pub fn start_browser() {
                             );
                             ..set_expand(true);
                     };
-                    
-                    // TODO: Finish
+
+                    let pass_buff = TextBuffer::builder().build();
+                    let pass = cascade! {
+                        Box::new(Orientation::Horizontal, 0);
+                            ..pack_start(
+                                &Label::new(Some("Password: ")),
+                                false, false, app.cfg.margin
+                            );..pack_start(
+                                &TextView::builder()
+                                    .hexpand(true).buffer(&pass_buff)
+                                    .visibility(false).build(),
+                                true, true, app.cfg.margin
+                            );
+                            ..set_expand(true);
+                    };
+
+                    let error_lbl = Label::new(None);
+
+                    content_area.pack_start(
+                        &uname, false, false, app.cfg.margin
+                    );
+                    content_area.pack_start(
+                        &pass, false, false, app.cfg.margin
+                    );
+                    content_area.pack_start(
+                        &error_lbl, false, false, app.cfg.margin
+                    );
+
+                    let submit_tx = tx.clone();
+                    dialog.connect_response(move |view, resp| {
+                        if resp != ResponseType::Accept {
+                            view.hide();
+                            return;
+                        }
+
+                        let tx = submit_tx.clone();
+                        let uname = uname_buff.text(
+                            &uname_buff.start_iter(), &uname_buff.end_iter(),
+                            true
+                        ).map(|v| v.to_string()).unwrap_or_default();
+                        let pass = pass_buff.text(
+                            &pass_buff.start_iter(), &pass_buff.end_iter(),
+                            true
+                        ).map(|v| v.to_string()).unwrap_or_default();
+                        spawn(async move {
+                            let _ = tx.send(Event {
+                                tp: EventType::SyncSubmit(uname, pass),
+                                url: String::new(), tab: 0
+                            }).await;
+                        });
+                    });
+
+                    app.sync_dialog = Some(dialog.clone());
+                    app.sync_error = Some(error_lbl);
+
+                    dialog.show_all();
+                }, EventType::SyncSubmit(username, password) => {
+                    if app.cfg.salt.is_empty() {
+                        app.cfg.salt = crypto::b64_encode(&crypto::gen_salt());
+                        if store(APP_NAME, app.cfg.clone()).is_err() {
+                            warn!("Failed to persist sync salt.");
+                        }
+                    }
+
+                    // Fetching the remote blob means a blocking network
+                    // call, so it happens on a background thread; the
+                    // actual decrypt-and-merge happens once
+                    // SyncRemoteFetched reports back what it found
+                    fetch_synced_bookmarks(
+                        tx.clone(), app.cfg.sync_server.clone(),
+                        username, password
+                    );
+                }, EventType::SyncRemoteFetched(username, password, remote) => {
+                    let salt = match crypto::b64_decode(&app.cfg.salt) {
+                        Some(raw) => raw,
+                        None => {
+                            warn!("Corrupt sync salt in config!");
+                            continue;
+                        }
+                    };
+                    let key = crypto::derive_key(&password, &salt);
+
+                    // Merge whatever was already synced under this
+                    // account before we overwrite it with this session's
+                    // bookmarks
+                    if let Some(remote_bytes) = remote {
+                        let remote_blob = match String::from_utf8(remote_bytes) {
+                            Ok(blob) => blob,
+                            Err(_) => {
+                                warn!("Corrupt remote sync blob.");
+                                continue;
+                            }
+                        };
+                        match crypto::decrypt(&remote_blob, &key) {
+                            Err(_) => {
+                                warn!("Wrong password for bookmark sync.");
+                                if let Some(lbl) = &app.sync_error {
+                                    lbl.set_text("Incorrect password.");
+                                }
+                                continue;
+                            }, Ok(raw) => {
+                                if let Ok(synced) = serde_json::from_slice::<
+                                    Vec<Vec<Vec<String>>>
+                                >(&raw) {
+                                    for folder in synced {
+                                        if !app.cfg.bookmarks.contains(
+                                            &folder
+                                        ) {
+                                            app.cfg.bookmarks.push(folder);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    let blob =
+                        serde_json::to_vec(&app.cfg.bookmarks)
+                            .unwrap_or_default();
+                    let enc = crypto::encrypt(&blob, &key);
+                    app.cfg.pass_enc = enc.clone();
+                    app.cfg.username = username.clone();
+                    app.cfg.local = false;
+
+                    if store(APP_NAME, app.cfg.clone()).is_err() {
+                        warn!("Failed to persist config after sync.");
+                    }
+                    info!("Synced bookmarks for {}.", app.cfg.username);
+
+                    // Push the merged result back up so other installs
+                    // signed into this account can pull it down in turn
+                    upload_synced_bookmarks(
+                        app.cfg.sync_server.clone(), username, enc
+                    );
+
+                    if let Some(dialog) = app.sync_dialog.take() {
+                        dialog.hide();
+                    }
+                    app.sync_error = None;
+                }, EventType::ShowHistory => {
+                    let dialog = cascade! {
+                        Dialog::with_buttons(
+                            Some("History"), Some(&app.win),
+                            DialogFlags::from_bits(1).unwrap(),
+                            &[ ("_Close", ResponseType::Close) ]
+                        );
+                        ..connect_response(move |view, _| {
+                            view.hide();
+                        });
+                        ..set_default_size(480, 360);
+                    };
+                    let content_area = dialog.content_area();
+
+                    let history = app.history.clone();
+                    let render = move |query: &str| {
+                        history.search(query).iter()
+                            .map(|e| format!("{}\t{}", e.title, e.url))
+                            .collect::<Vec<String>>()
+                            .join("\n")
+                    };
+
+                    let results_buff = TextBuffer::builder().build();
+                    results_buff.set_text(&render(""));
+                    let results = TextView::builder()
+                        .editable(false).cursor_visible(false)
+                        .buffer(&results_buff).build();
+
+                    let search_buff = results_buff.clone();
+                    let search = cascade! {
+                        SearchEntry::builder().build();
+                            ..connect_search_changed(move |entry| {
+                                search_buff.set_text(&render(
+                                    entry.text().as_str()
+                                ));
+                            });
+                    };
+
+                    content_area.pack_start(
+                        &search, false, false, app.cfg.margin
+                    );
+                    content_area.pack_start(
+                        &results, true, true, app.cfg.margin
+                    );
 
                     dialog.show_all();
+                }, EventType::DownloadStarted(url, dest) => {
+                    info!("Download started: {} -> {}.", url, dest);
+
+                    // RetryDownload reissues the same url/dest through
+                    // load_request, so WebKit re-fires download-started for
+                    // the resumed transfer. Reuse the interrupted record
+                    // rather than pushing a duplicate one next to it, and
+                    // remember how much we'd already received so this
+                    // attempt's (ranged) progress reports can be added on
+                    // top instead of overwriting the cumulative total.
+                    match app.downloads.find(&dest) {
+                        Some(rec) => {
+                            rec.resume_offset = rec.bytes_received;
+                            rec.finished = false;
+                            rec.interrupted = false;
+                        }, None => {
+                            app.downloads.records.push(
+                                downloads::DownloadRecord {
+                                    url, dest,
+                                    bytes_received: 0,
+                                    total_bytes: 0,
+                                    finished: false,
+                                    interrupted: false,
+                                    resume_offset: 0
+                                }
+                            );
+                        }
+                    }
+                    app.downloads.save(APP_NAME);
+                    app.refresh_downloads_ui(&tx);
+                }, EventType::DownloadProgress(dest, received, total) => {
+                    if let Some(rec) = app.downloads.find(&dest) {
+                        // `received`/`total` are relative to the current
+                        // attempt's own response; add the offset from any
+                        // prior interrupted attempts to get the true,
+                        // whole-file position
+                        rec.bytes_received = rec.resume_offset + received;
+                        rec.total_bytes = rec.resume_offset + total;
+                    }
+                    app.refresh_downloads_ui(&tx);
+                }, EventType::DownloadFinished(dest) => {
+                    if let Some(rec) = app.downloads.find(&dest) {
+                        rec.finished = true;
+                        rec.interrupted = false;
+                        info!("Download finished: {}.", dest);
+                    }
+                    app.downloads.save(APP_NAME);
+                    app.refresh_downloads_ui(&tx);
+                }, EventType::DownloadFailed(dest) => {
+                    if let Some(rec) = app.downloads.find(&dest) {
+                        rec.interrupted = true;
+                        warn!("Download interrupted: {}.", dest);
+                    }
+                    app.downloads.save(APP_NAME);
+                    app.refresh_downloads_ui(&tx);
+                }, EventType::RetryDownload(dest) => {
+                    let found = app.downloads.find(&dest)
+                        .map(|rec| (rec.url.clone(), rec.bytes_received));
+                    if let Some((url, received)) = found {
+                        // Resume from where we left off. WebKit re-fires
+                        // download-started once the server responds with
+                        // the (still-attachment) file.
+                        let request = URIRequest::new(&url);
+                        if let Some(headers) = request.http_headers() {
+                            headers.append(
+                                "Range",
+                                format!("bytes={}-", received).as_str()
+                            );
+                        }
+                        app.tabs[active_tab].web_view.load_request(&request);
+                    }
+                }, EventType::FaviconDownloaded(host, bytes) => {
+                    if let Some(path) =
+                        app.favicons.record(APP_NAME, &host, &bytes)
+                    {
+                        let active_host = app.tabs[active_tab].back_urls
+                            .last().and_then(|url| host_of(url));
+                        if active_host.as_deref() == Some(host.as_str()) {
+                            if let Ok(pixbuf) = Pixbuf::from_file(&path) {
+                                app.win.set_icon(Some(&pixbuf));
+                            }
+                        }
+
+                        if let Some(icon) = app.bookmark_icons.get(&host) {
+                            icon.set_from_file(Some(&path));
+                        }
+                    }
+                }, EventType::AddBookmark(url) => {
+                    let already_bookmarked = app.cfg.bookmarks.iter().any(
+                        |folder| folder.len() == 1 && folder[0].len() == 1
+                            && folder[0][0] == url
+                    );
+                    if already_bookmarked {
+                        continue;
+                    }
+
+                    app.cfg.bookmarks.push(vec![ vec![ url.clone() ] ]);
+                    if store(APP_NAME, app.cfg.clone()).is_err() {
+                        warn!("Failed to persist bookmarks.");
+                    }
+                    info!("Bookmarked {}.", url);
+
+                    let (item, icon) = build_bookmark_item(url.as_str());
+                    if let Some(host) = host_of(&url) {
+                        app.bookmark_icons.insert(host, icon);
+                    }
+                    let item_tx = tx.clone();
+                    let item_url = url.clone();
+                    item.connect_activate(move |_| {
+                        let tx = item_tx.clone();
+                        let url = item_url.clone();
+                        spawn(async move {
+                            let _ = tx.send(Event {
+                                tp: EventType::ChangePage, url, tab: 0
+                            }).await;
+                        });
+                    });
+                    app.bookmark_menu.append(&item);
+                    app.bookmark_menu.show_all();
+                }, EventType::ResourceSeen(host, blocked) => {
+                    let tab = &mut app.tabs[event.tab];
+                    if !tab.seen_hosts.contains(&host) {
+                        tab.seen_hosts.push(host);
+                    }
+
+                    if blocked {
+                        tab.blocked_count += 1;
+                        if event.tab == active_tab {
+                            app.blocklist_btn.set_label(
+                                &format!("⛔ {}", tab.blocked_count)
+                            );
+                        }
+                    }
+                }, EventType::ShowBlocklist => {
+                    let dialog = cascade! {
+                        Dialog::with_buttons(
+                            Some("Request Filtering"), Some(&app.win),
+                            DialogFlags::from_bits(1).unwrap(),
+                            &[ ("_Close", ResponseType::Close) ]
+                        );
+                        ..connect_response(move |view, _| {
+                            view.hide();
+                        });
+                        ..set_default_size(420, 360);
+                    };
+                    let content_area = dialog.content_area();
+
+                    content_area.pack_start(
+                        &Label::new(Some("Blocked hosts:")),
+                        false, false, app.cfg.margin
+                    );
+                    let blocked_box = Box::new(Orientation::Vertical, 2);
+                    for pattern in app.blocklist.patterns.clone() {
+                        let row_tx = tx.clone();
+                        let row_pattern = pattern.clone();
+                        let row = cascade! {
+                            Box::new(Orientation::Horizontal, 4);
+                                ..pack_start(
+                                    &Label::new(Some(pattern.as_str())),
+                                    true, true, 0
+                                );
+                                ..pack_start(
+                                    &cascade! {
+                                        Button::with_label("Remove");
+                                            ..connect_clicked(move |_| {
+                                                let tx = row_tx.clone();
+                                                let pattern =
+                                                    row_pattern.clone();
+                                                spawn(async move {
+                                                    let _ = tx.send(Event {
+                                                        tp: EventType::
+                                                            RemoveBlockedHost(
+                                                                pattern
+                                                            ),
+                                                        url: String::new(),
+                                                        tab: 0
+                                                    }).await;
+                                                });
+                                            });
+                                    }, false, false, 0
+                                );
+                        };
+                        blocked_box.pack_start(&row, false, false, 0);
+                    }
+                    content_area.pack_start(
+                        &blocked_box, false, false, app.cfg.margin
+                    );
+
+                    content_area.pack_start(
+                        &Label::new(Some(
+                            "Third-party hosts seen on this page:"
+                        )), false, false, app.cfg.margin
+                    );
+                    let page_host = app.tabs[active_tab].back_urls.last()
+                        .and_then(|url| host_of(url));
+                    let seen_box = Box::new(Orientation::Vertical, 2);
+                    for host in app.tabs[active_tab].seen_hosts.clone() {
+                        if Some(&host) == page_host.as_ref()
+                            || app.blocklist.is_blocked(&host)
+                        {
+                            continue;
+                        }
+
+                        let row_tx = tx.clone();
+                        let row_host = host.clone();
+                        let row = cascade! {
+                            Box::new(Orientation::Horizontal, 4);
+                                ..pack_start(
+                                    &Label::new(Some(host.as_str())),
+                                    true, true, 0
+                                );
+                                ..pack_start(
+                                    &cascade! {
+                                        Button::with_label("Block");
+                                            ..connect_clicked(move |_| {
+                                                let tx = row_tx.clone();
+                                                let host = row_host.clone();
+                                                spawn(async move {
+                                                    let _ = tx.send(Event {
+                                                        tp: EventType::
+                                                            AddBlockedHost(
+                                                                host
+                                                            ),
+                                                        url: String::new(),
+                                                        tab: 0
+                                                    }).await;
+                                                });
+                                            });
+                                    }, false, false, 0
+                                );
+                        };
+                        seen_box.pack_start(&row, false, false, 0);
+                    }
+                    content_area.pack_start(
+                        &seen_box, false, false, app.cfg.margin
+                    );
+
+                    let add_entry = Entry::builder()
+                        .placeholder_text("host or *.example.com").build();
+                    let add_tx = tx.clone();
+                    let entry_for_btn = add_entry.clone();
+                    let add_row = cascade! {
+                        Box::new(Orientation::Horizontal, 4);
+                            ..pack_start(&add_entry, true, true, 0);
+                            ..pack_start(
+                                &cascade! {
+                                    Button::with_label("Add");
+                                        ..connect_clicked(move |_| {
+                                            let pattern =
+                                                entry_for_btn.text()
+                                                    .to_string();
+                                            if pattern.is_empty() {
+                                                return;
+                                            }
+
+                                            let tx = add_tx.clone();
+                                            spawn(async move {
+                                                let _ = tx.send(Event {
+                                                    tp: EventType::
+                                                        AddBlockedHost(
+                                                            pattern
+                                                        ),
+                                                    url: String::new(),
+                                                    tab: 0
+                                                }).await;
+                                            });
+                                        });
+                                }, false, false, 0
+                            );
+                    };
+                    content_area.pack_start(
+                        &add_row, false, false, app.cfg.margin
+                    );
+
+                    dialog.show_all();
+                }, EventType::AddBlockedHost(host) => {
+                    app.blocklist.add(APP_NAME, &host);
+                    *app.blocklist_shared.borrow_mut() =
+                        app.blocklist.patterns.clone();
+                    info!("Added {} to the request blocklist.", host);
+                }, EventType::RemoveBlockedHost(host) => {
+                    app.blocklist.remove(APP_NAME, &host);
+                    *app.blocklist_shared.borrow_mut() =
+                        app.blocklist.patterns.clone();
+                    info!("Removed {} from the request blocklist.", host);
                 }
             }
         }
@@ -157,14 +1109,20 @@ pub fn start_browser() {
     main();
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 struct AppConfig {
     pub start_page: String,
     pub search_engine: String,
     pub local: bool,
     pub bookmarks: Vec<Vec<Vec<String>>>,
     pub username: String,
+    // Base64 Argon2id salt used to derive the sync key from the password
+    pub salt: String,
+    // Base64(nonce || AES-256-GCM ciphertext) of the synced bookmarks blob
     pub pass_enc: String,
+    // Where the encrypted blob above gets uploaded/downloaded from; point
+    // this at a self-hosted instance to sync between your own installs
+    pub sync_server: String,
     pub margin: u32
 }
 
@@ -176,7 +1134,9 @@ impl Default for AppConfig {
             local: false,
             bookmarks: Vec::new(),
             username: String::new(),
+            salt: String::new(),
             pass_enc: String::new(),
+            sync_server: String::from("https://sync.example.com"),
             margin: 10
         }
     }
@@ -189,19 +1149,124 @@ enum EventType {
     ChangedPage,
     ChangePage,
     FailedChangePage,
-    LoginRegister
+    LoginRegister,
+    SyncSubmit(String, String),
+    // username, password, still-encrypted remote blob (None if there was
+    // nothing synced yet, or the server couldn't be reached)
+    SyncRemoteFetched(String, String, Option<Vec<u8>>),
+    NewTab,
+    CloseTab,
+    SwitchTab(usize),
+    ShowHistory,
+    // Downloads are keyed by destination path rather than an index, since
+    // the Download signal closures are wired up before a record exists
+    DownloadStarted(String, String),
+    DownloadProgress(String, u64, u64),
+    DownloadFinished(String),
+    DownloadFailed(String),
+    RetryDownload(String),
+    FaviconDownloaded(String, Vec<u8>),
+    AddBookmark(String),
+    // host, was-blocked
+    ResourceSeen(String, bool),
+    ShowBlocklist,
+    AddBlockedHost(String),
+    RemoveBlockedHost(String)
 }
 
 struct Event {
     pub tp: EventType,
-    pub url: String
+    pub url: String,
+    pub tab: usize
+}
+
+// A single browser tab: its own page and its own back/forward history
+struct Tab {
+    pub web_view: WebView,
+    pub back_urls: Vec<String>,
+    pub fwd_urls: Vec<Option<String>>,
+    pub via_nav_btns: bool,
+    pub err_url: String,
+    // Reset on every navigation; drives the nav bar's blocklist tally
+    pub blocked_count: u32,
+    pub seen_hosts: Vec<String>,
+    // Shared with this tab's WebView/label closures so they keep tagging
+    // events with the right position after a lower-numbered tab closes
+    pub id: Rc<Cell<usize>>
 }
 
 struct AppState {
     pub win: Window,
-    pub web_view: WebView,
+    pub notebook: Notebook,
+    pub tabs: Vec<Tab>,
     pub cfg: AppConfig,
-    pub tb_buff: TextBuffer
+    pub tb_buff: TextBuffer,
+    pub history: History,
+    // Kept so EventType::SyncRemoteFetched can report back into the still-open
+    // login dialog instead of silently hiding it on a wrong password
+    pub sync_dialog: Option<Dialog>,
+    pub sync_error: Option<Label>,
+    pub downloads: Downloads,
+    pub downloads_box: Box,
+    pub favicons: FaviconCache,
+    pub bookmark_menu: Menu,
+    // Bookmark menu icons, keyed by the host they were bookmarked under
+    pub bookmark_icons: HashMap<String, Image>,
+    pub blocklist: Blocklist,
+    // Mirrors blocklist.patterns; shared with every tab's WebView closure so
+    // resource-load-started can make a synchronous block/allow decision
+    pub blocklist_shared: Rc<RefCell<Vec<String>>>,
+    pub blocklist_btn: Button
+}
+
+impl AppState {
+    // Rebuilds the downloads popover's rows from the current download list
+    fn refresh_downloads_ui(&self, tx: &Sender<Event>) {
+        for child in self.downloads_box.children() {
+            self.downloads_box.remove(&child);
+        }
+
+        for record in self.downloads.records.iter().rev() {
+            let row = cascade! {
+                Box::new(Orientation::Horizontal, 4);
+            };
+
+            let frac = if record.total_bytes > 0 {
+                record.bytes_received as f64 / record.total_bytes as f64
+            } else {
+                0.0
+            };
+            let bar = cascade! {
+                ProgressBar::builder().hexpand(true).fraction(frac).build();
+                    ..set_text(Some(record.dest.as_str()));
+                    ..set_show_text(true);
+            };
+            row.pack_start(&bar, true, true, self.cfg.margin);
+
+            if record.interrupted {
+                let retry_tx = tx.clone();
+                let dest = record.dest.clone();
+                let retry_btn = cascade! {
+                    Button::with_label("Retry");
+                        ..connect_clicked(move |_| {
+                            let tx = retry_tx.clone();
+                            let dest = dest.clone();
+                            spawn(async move {
+                                let _ = tx.send(Event {
+                                    tp: EventType::RetryDownload(dest),
+                                    url: String::new(), tab: 0
+                                }).await;
+                            });
+                        });
+                };
+                row.pack_start(&retry_btn, false, false, self.cfg.margin);
+            }
+
+            self.downloads_box.pack_start(&row, false, false, 0);
+        }
+
+        self.downloads_box.show_all();
+    }
 }
 
 impl AppState {
@@ -213,14 +1278,15 @@ impl AppState {
                 AppConfig::default()
             }, Ok(config) => config
         };
-        if !temp_cfg.local {
-            // Sync via db
-            let synced_bm = Vec::new();
-
-            if false {
-                temp_cfg.bookmarks = synced_bm.clone();
-                store(APP_NAME, temp_cfg).unwrap();
-            }
+        if !temp_cfg.local && !temp_cfg.pass_enc.is_empty() {
+            // Merging the synced bookmarks needs the sync key, which is
+            // derived from the password. We deliberately never persist
+            // the password (or the derived key) across runs, so no merge
+            // happens at startup; this only logs that synced bookmarks
+            // exist. Merging happens in EventType::SyncRemoteFetched after
+            // a sign-in, and the sync button that opens the sign-in dialog
+            // is only shown when `local` is set.
+            info!("Synced bookmarks pending: sign in to merge them.");
         }
 
         // Load config file
@@ -232,6 +1298,10 @@ impl AppState {
         };
         let start_page = cfg.start_page.clone();
 
+        let history = History::load(APP_NAME);
+        let downloads = Downloads::load(APP_NAME);
+        let favicons = FaviconCache::load(APP_NAME);
+
         /* Create navigation bar */
 
         // Back button
@@ -243,7 +1313,8 @@ impl AppState {
                     let tx = back_tx.clone();
                     spawn(async move {
                         let _ = tx.send(Event {
-                            tp: EventType::BackClicked, url: String::new()
+                            tp: EventType::BackClicked,
+                            url: String::new(), tab: 0
                         }).await;
                     });
                 });
@@ -258,7 +1329,8 @@ impl AppState {
                     let tx = fwd_tx.clone();
                     spawn(async move {
                         let _ = tx.send(Event {
-                            tp: EventType::ForwardClicked, url: String::new()
+                            tp: EventType::ForwardClicked,
+                            url: String::new(), tab: 0
                         }).await;
                     });
                 });
@@ -277,7 +1349,7 @@ impl AppState {
                             None => String::new(),
                             Some(val) => val.to_string()
                         };
-                        
+
                         let lines = txt.split("\n");
                         let val: String = lines.collect();
                         tb_buff.set_text(&val);
@@ -285,7 +1357,7 @@ impl AppState {
                         spawn(async move {
                             let _ = tx.send(Event {
                                 tp: EventType::ChangePage,
-                                url: val
+                                url: val, tab: 0
                             }).await;
                         });
                     }
@@ -295,8 +1367,61 @@ impl AppState {
             TextView::builder().hexpand(true).accepts_tab(false)
                 .valign(Align::Center).buffer(&buff).build();
 
+        // Autocomplete popover, seeded from persisted history
+        let history_urls: Vec<String> =
+            history.entries.iter().rev().map(|e| e.url.clone()).collect();
+        let complete_box = Box::new(Orientation::Vertical, 0);
+        let complete_popover = cascade! {
+            Popover::builder().build();
+                ..set_relative_to(Some(&tb));
+                ..add(&complete_box);
+        };
+        buff.connect_changed(move |tb_buff| {
+            // The navigate-on-Enter branch is handled above; this only
+            // drives suggestions while the user is still typing
+            if tb_buff.line_count() > 1 {
+                return;
+            }
+
+            let txt = match tb_buff.text(
+                &tb_buff.start_iter(), &tb_buff.end_iter(), true
+            ) {
+                None => String::new(),
+                Some(val) => val.to_string()
+            };
+
+            for child in complete_box.children() {
+                complete_box.remove(&child);
+            }
+
+            if txt.is_empty() {
+                complete_popover.popdown();
+                return;
+            }
+
+            let needle = txt.to_lowercase();
+            for url in history_urls.iter()
+                .filter(|url| url.to_lowercase().contains(&needle)).take(5)
+            {
+                let item_buff = tb_buff.clone();
+                let item_popover = complete_popover.clone();
+                let url = url.clone();
+                let item = cascade! {
+                    Button::with_label(url.as_str());
+                        ..connect_clicked(move |_| {
+                            item_buff.set_text(&url);
+                            item_popover.popdown();
+                        });
+                };
+                complete_box.add(&item);
+            }
+            complete_box.show_all();
+            complete_popover.popup();
+        });
+
         // Generate book marks menu
         let bookmark_menu = Menu::builder().build();
+        let mut bookmark_icons = HashMap::new();
         for folder in cfg.bookmarks.clone() {
             match folder.len() {
                 0 => { },
@@ -308,20 +1433,22 @@ impl AppState {
 
                     info!("Found local bookmark: {} -> '{}'.", name, bm_url);
 
+                    let (item, icon) = build_bookmark_item(name.as_str());
+                    if let Some(host) = host_of(&bm_url) {
+                        bookmark_icons.insert(host, icon);
+                    }
+
                     let item_tx = tx.clone();
-                    let item = cascade! {
-                        MenuItem::with_label(name.as_str());
-                            ..connect_activate(move |_| {
-                                let tx = item_tx.clone();
-                                let url = bm_url.clone();
-                                spawn(async move {
-                                    let _ = tx.send(Event {
-                                        tp: EventType::ChangePage,
-                                        url
-                                    }).await;
-                                });
-                            });
-                    };
+                    item.connect_activate(move |_| {
+                        let tx = item_tx.clone();
+                        let url = bm_url.clone();
+                        spawn(async move {
+                            let _ = tx.send(Event {
+                                tp: EventType::ChangePage,
+                                url, tab: 0
+                            }).await;
+                        });
+                    });
                     bookmark_menu.append(&item);
                 }, _ => {
                     let fldr_name = folder[0][0].clone();
@@ -339,20 +1466,22 @@ impl AppState {
                             fldr_name, name, bm_url
                         );
 
+                        let (item, icon) = build_bookmark_item(name.as_str());
+                        if let Some(host) = host_of(&bm_url) {
+                            bookmark_icons.insert(host, icon);
+                        }
+
                         let item_tx = tx.clone();
-                        let item = cascade! {
-                            MenuItem::with_label(name.as_str());
-                                ..connect_activate(move |_| {
-                                    let tx = item_tx.clone();
-                                    let url = bm_url.clone();
-                                    spawn(async move {
-                                        let _ = tx.send(Event {
-                                            tp: EventType::ChangePage,
-                                            url
-                                        }).await;
-                                    });
-                                });
-                        };
+                        item.connect_activate(move |_| {
+                            let tx = item_tx.clone();
+                            let url = bm_url.clone();
+                            spawn(async move {
+                                let _ = tx.send(Event {
+                                    tp: EventType::ChangePage,
+                                    url, tab: 0
+                                }).await;
+                            });
+                        });
                         sub_menu.append(&item);
                     }
 
@@ -372,6 +1501,18 @@ impl AppState {
                 ..set_popup(Some(&bookmark_menu));
         };
 
+        // Downloads popover, sits right next to the bookmarks button
+        let downloads_box = Box::new(Orientation::Vertical, 4);
+        let downloads_popover = cascade! {
+            Popover::builder().build();
+                ..add(&downloads_box);
+        };
+        let dl_btn = cascade! {
+            MenuButton::builder().label("⇩").build();
+                ..set_border_width(cfg.margin);
+                ..set_popover(Some(&downloads_popover));
+        };
+
         let refr_tx = tx.clone();
         let refr_btn = cascade! {
             Button::with_label("↺");
@@ -380,46 +1521,192 @@ impl AppState {
                     let tx = refr_tx.clone();
                     spawn(async move {
                         let _ = tx.send(Event {
-                            tp: EventType::RefreshClicked, url: String::new()
+                            tp: EventType::RefreshClicked,
+                            url: String::new(), tab: 0
                         }).await;
                     });
                 });
         };
 
-        /* Create page view */
-        let web_tx1 = tx.clone();
-        let web_tx2 = tx.clone();
-        let web_view = cascade! {
-            WebView::builder().build();
-                ..load_uri(&start_page);
-                ..connect_load_changed(move |view, load_ev| {
-                    if load_ev == LoadEvent::Started {
-                        let tx = web_tx1.clone();
-                        let txt = WebView::uri(&view).unwrap().to_string();
+        // History button
+        let hist_tx = tx.clone();
+        let hist_btn = cascade! {
+            Button::with_label("H");
+                ..set_border_width(cfg.margin);
+                ..connect_clicked(move |_| {
+                    let tx = hist_tx.clone();
+                    spawn(async move {
+                        let _ = tx.send(Event {
+                            tp: EventType::ShowHistory,
+                            url: String::new(), tab: 0
+                        }).await;
+                    });
+                });
+        };
+
+        // New tab button
+        let new_tab_tx = tx.clone();
+        let new_tab_btn = cascade! {
+            Button::with_label("+");
+                ..set_border_width(cfg.margin);
+                ..connect_clicked(move |_| {
+                    let tx = new_tab_tx.clone();
+                    spawn(async move {
+                        let _ = tx.send(Event {
+                            tp: EventType::NewTab,
+                            url: String::new(), tab: 0
+                        }).await;
+                    });
+                });
+        };
+
+        // All WebViews share the default WebContext, so a single hookup
+        // here catches downloads started from any tab
+        if let Some(web_ctx) = WebContext::default() {
+            let dl_tx = tx.clone();
+            web_ctx.connect_download_started(move |_, download| {
+                let url = download.request()
+                    .map(|req| req.uri().map(|u| u.to_string())
+                        .unwrap_or_default())
+                    .unwrap_or_default();
+
+                let downloads_dir = home_dir()
+                    .unwrap_or_default().join("Downloads");
+                let _ = create_dir_all(&downloads_dir);
+
+                // WebKit doesn't pick a destination on its own; nothing
+                // downstream (the record, the retry logic) has a file to
+                // key on until we choose one here and hand it back
+                let dl_tx = dl_tx.clone();
+                download.connect_decide_destination(
+                    move |download, suggested_filename| {
+                        let dest = downloads_dir.join(suggested_filename)
+                            .display().to_string();
+                        download.set_destination(&dest);
+
+                        let started_tx = dl_tx.clone();
+                        let started_url = url.clone();
+                        let started_dest = dest.clone();
                         spawn(async move {
-                            let _ = tx.send(Event {
-                                tp: EventType::ChangedPage,
-                                url: txt
+                            let _ = started_tx.send(Event {
+                                tp: EventType::DownloadStarted(
+                                    started_url, started_dest
+                                ),
+                                url: String::new(), tab: 0
                             }).await;
                         });
+
+                        let progress_tx = dl_tx.clone();
+                        let progress_dest = dest.clone();
+                        download.connect_received_data(move |download, _len| {
+                            let total = download.response()
+                                .map(|resp| resp.content_length())
+                                .unwrap_or(0);
+                            let received = (download.estimated_progress()
+                                * total as f64) as u64;
+
+                            let tx = progress_tx.clone();
+                            let dest = progress_dest.clone();
+                            spawn(async move {
+                                let _ = tx.send(Event {
+                                    tp: EventType::DownloadProgress(
+                                        dest, received, total
+                                    ),
+                                    url: String::new(), tab: 0
+                                }).await;
+                            });
+                        });
+
+                        let finished_tx = dl_tx.clone();
+                        let finished_dest = dest.clone();
+                        download.connect_finished(move |_| {
+                            let tx = finished_tx.clone();
+                            let dest = finished_dest.clone();
+                            spawn(async move {
+                                let _ = tx.send(Event {
+                                    tp: EventType::DownloadFinished(dest),
+                                    url: String::new(), tab: 0
+                                }).await;
+                            });
+                        });
+
+                        let failed_tx = dl_tx.clone();
+                        let failed_dest = dest.clone();
+                        download.connect_failed(move |_, _| {
+                            let tx = failed_tx.clone();
+                            let dest = failed_dest.clone();
+                            spawn(async move {
+                                let _ = tx.send(Event {
+                                    tp: EventType::DownloadFailed(dest),
+                                    url: String::new(), tab: 0
+                                }).await;
+                            });
+                        });
+
+                        true
                     }
-                });
-                ..connect_load_failed(move |_, _, uri, _| {
-                    let tx = web_tx2.clone();
-                    let url = String::from(uri);
+                );
+            });
+        }
+
+        /* Create the tab strip, starting with a single tab */
+        let switch_tx = tx.clone();
+        let notebook = cascade! {
+            Notebook::builder().scrollable(true).build();
+                ..connect_switch_page(move |_, _, page_num| {
+                    let tx = switch_tx.clone();
+                    let idx = page_num as usize;
                     spawn(async move {
                         let _ = tx.send(Event {
-                            tp: EventType::FailedChangePage,
-                            url
+                            tp: EventType::SwitchTab(idx),
+                            url: String::new(), tab: idx
                         }).await;
                     });
-                    true
                 });
         };
+
+        let blocklist = Blocklist::load(APP_NAME);
+        let blocklist_shared = Rc::new(RefCell::new(blocklist.patterns.clone()));
+
+        let tab_id = Rc::new(Cell::new(0));
+        let web_view = build_web_view(
+            tx.clone(), tab_id.clone(), &start_page, blocklist_shared.clone()
+        );
         let web_box = cascade! {
             Box::new(Orientation::Horizontal, 0);
                 ..pack_start(&web_view, true, true, cfg.margin);
         };
+        notebook.append_page(
+            &web_box, Some(&build_tab_label(tx.clone(), tab_id.clone()))
+        );
+        notebook.set_tab_reorderable(&web_box, true);
+
+        let tabs = vec![ Tab {
+            web_view,
+            back_urls: vec![ start_page ],
+            fwd_urls: Vec::new(),
+            via_nav_btns: false,
+            err_url: String::new(),
+            blocked_count: 0,
+            seen_hosts: Vec::new(),
+            id: tab_id
+        } ];
+
+        // Blocklist toggle button, shows the active tab's blocked tally
+        let blk_tx = tx.clone();
+        let blocklist_btn = cascade! {
+            Button::with_label("⛔ 0");
+                ..set_border_width(cfg.margin);
+                ..connect_clicked(move |_| {
+                    let tx = blk_tx.clone();
+                    spawn(async move {
+                        let _ = tx.send(Event {
+                            tp: EventType::ShowBlocklist,
+                            url: String::new(), tab: 0
+                        }).await;
+                    });
+                });
+        };
 
         /* Put it all together */
         let view_cont = cascade! {
@@ -428,7 +1715,11 @@ impl AppState {
                 ..attach(&fwd_btn, 1, 0, 1, 1);
                 ..attach(&tb, 2, 0, 5, 1);
                 ..attach(&bm_btn, 7, 0, 1, 1);
-                ..attach(&refr_btn, 8, 0, 1, 1);
+                ..attach(&dl_btn, 8, 0, 1, 1);
+                ..attach(&refr_btn, 9, 0, 1, 1);
+                ..attach(&hist_btn, 10, 0, 1, 1);
+                ..attach(&new_tab_btn, 11, 0, 1, 1);
+                ..attach(&blocklist_btn, 12, 0, 1, 1);
         };
 
         // Sync popup button
@@ -442,18 +1733,18 @@ impl AppState {
                         spawn(async move {
                             let _ = tx.send(Event {
                                 tp: EventType::LoginRegister,
-                                url: String::new()
+                                url: String::new(), tab: 0
                             }).await;
                         });
                     });
             };
-            view_cont.attach(&sync_btn, 9, 0, 1, 1);
+            view_cont.attach(&sync_btn, 13, 0, 1, 1);
         }
 
         let view = cascade! {
             Box::new(Orientation::Vertical, 0);
                 ..pack_start(&view_cont, false, false, 0);
-                ..pack_end(&web_box, true, true, cfg.margin);
+                ..pack_end(&notebook, true, true, cfg.margin);
         };
         let win = cascade! {
             Window::new(WindowType::Toplevel);
@@ -470,9 +1761,21 @@ impl AppState {
 
         Self {
             win,
-            web_view,
+            notebook,
+            tabs,
             cfg,
-            tb_buff: buff
+            tb_buff: buff,
+            history,
+            sync_dialog: None,
+            sync_error: None,
+            downloads,
+            downloads_box,
+            favicons,
+            bookmark_menu,
+            bookmark_icons,
+            blocklist,
+            blocklist_shared,
+            blocklist_btn
         }
     }
 }
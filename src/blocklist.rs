@@ -0,0 +1,90 @@
+/*
+ * Author: Dylan Turner
+ * Description: Host-based request filtering, backed by a user blocklist file
+ */
+
+use std::fs::{ read_to_string, write };
+use std::path::PathBuf;
+
+const BLOCKLIST_FILE: &'static str = "blocklist.txt";
+
+// One host pattern per line, '*' wildcard supported (e.g. "*.doubleclick.net")
+pub struct Blocklist {
+    pub patterns: Vec<String>
+}
+
+impl Blocklist {
+    pub fn load(app_name: &str) -> Self {
+        let patterns = match read_to_string(Self::path(app_name)) {
+            Err(_) => Vec::new(),
+            Ok(raw) => raw.lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .collect()
+        };
+        Self { patterns }
+    }
+
+    pub fn save(&self, app_name: &str) {
+        let _ = write(Self::path(app_name), self.patterns.join("\n"));
+    }
+
+    pub fn is_blocked(&self, host: &str) -> bool {
+        self.patterns.iter().any(|pattern| matches(pattern, host))
+    }
+
+    pub fn add(&mut self, app_name: &str, host: &str) {
+        if !self.patterns.iter().any(|p| p == host) {
+            self.patterns.push(host.to_string());
+            self.save(app_name);
+        }
+    }
+
+    pub fn remove(&mut self, app_name: &str, host: &str) {
+        self.patterns.retain(|p| p != host);
+        self.save(app_name);
+    }
+
+    fn path(app_name: &str) -> PathBuf {
+        let mut path = confy::get_configuration_file_path(app_name, None)
+            .unwrap_or_else(|_| PathBuf::from("."));
+        path.pop();
+        path.push(BLOCKLIST_FILE);
+        path
+    }
+}
+
+// Simple glob match supporting '*' wildcards, no regex dependency needed
+pub fn matches(pattern: &str, host: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == host;
+    }
+
+    let mut rest = host;
+    for (i, part) in parts.iter().enumerate() {
+        if part.is_empty() {
+            continue;
+        }
+
+        if i == 0 {
+            if !rest.starts_with(part) {
+                return false;
+            }
+            rest = &rest[part.len()..];
+        } else if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        } else {
+            match rest.find(part) {
+                None => return false,
+                Some(pos) => rest = &rest[pos + part.len()..]
+            }
+        }
+    }
+
+    true
+}
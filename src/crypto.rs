@@ -0,0 +1,65 @@
+/*
+ * Author: Dylan Turner
+ * Description: Key derivation and authenticated encryption for bookmark sync
+ */
+
+use aes_gcm::{
+    aead::{ Aead, KeyInit, OsRng, rand_core::RngCore, generic_array::GenericArray },
+    Aes256Gcm, Nonce
+};
+use argon2::Argon2;
+use base64::{ engine::general_purpose::STANDARD, Engine };
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+pub fn gen_salt() -> [u8; SALT_LEN] {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+// Derives a 256-bit key from a password and a (persisted) random salt
+pub fn derive_key(password: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .expect("static Argon2id params are always valid");
+    key
+}
+
+// Encrypts `plaintext` under `key`, returning base64(nonce || ciphertext)
+pub fn encrypt(plaintext: &[u8], key: &[u8; 32]) -> String {
+    let cipher = Aes256Gcm::new(GenericArray::from_slice(key));
+
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let nonce = Nonce::from_slice(&nonce_bytes);
+
+    let mut out = nonce_bytes.to_vec();
+    out.extend(cipher.encrypt(nonce, plaintext)
+        .expect("encryption cannot fail for a 12-byte nonce"));
+
+    b64_encode(&out)
+}
+
+// Decrypts base64(nonce || ciphertext) produced by `encrypt`. Fails (rather
+// than panics) on a wrong password so callers can report it back to the user
+pub fn decrypt(blob: &str, key: &[u8; 32]) -> Result<Vec<u8>, ()> {
+    let raw = b64_decode(blob).ok_or(())?;
+    if raw.len() < NONCE_LEN {
+        return Err(());
+    }
+
+    let (nonce_bytes, ciphertext) = raw.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(GenericArray::from_slice(key));
+    cipher.decrypt(Nonce::from_slice(nonce_bytes), ciphertext).map_err(|_| ())
+}
+
+pub fn b64_encode(data: &[u8]) -> String {
+    STANDARD.encode(data)
+}
+
+pub fn b64_decode(data: &str) -> Option<Vec<u8>> {
+    STANDARD.decode(data).ok()
+}
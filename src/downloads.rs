@@ -0,0 +1,68 @@
+/*
+ * Author: Dylan Turner
+ * Description: Tracks active and completed downloads, persisted across runs
+ */
+
+use std::fs::{ read_to_string, write };
+use std::path::PathBuf;
+use serde::{ Serialize, Deserialize };
+use log::warn;
+
+const DOWNLOADS_FILE: &'static str = "downloads.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DownloadRecord {
+    pub url: String,
+    pub dest: String,
+    // Cumulative across every attempt, not just the current (possibly
+    // ranged) one; see resume_offset
+    pub bytes_received: u64,
+    pub total_bytes: u64,
+    pub finished: bool,
+    pub interrupted: bool,
+    // bytes_received as of the start of the current attempt. A resumed
+    // download's own progress reports are relative to its own (ranged)
+    // response, so they're added on top of this to get the true offset
+    pub resume_offset: u64
+}
+
+// Stored as its own file next to the confy config, same reasoning as History
+#[derive(Serialize, Deserialize, Default)]
+pub struct Downloads {
+    pub records: Vec<DownloadRecord>
+}
+
+impl Downloads {
+    pub fn load(app_name: &str) -> Self {
+        match read_to_string(Self::path(app_name)) {
+            Err(_) => Downloads::default(),
+            Ok(raw) => match serde_json::from_str(&raw) {
+                Err(_) => {
+                    warn!("Error in downloads file! Starting empty.");
+                    Downloads::default()
+                }, Ok(downloads) => downloads
+            }
+        }
+    }
+
+    pub fn save(&self, app_name: &str) {
+        match serde_json::to_string_pretty(self) {
+            Err(_) => warn!("Failed to serialize downloads!"),
+            Ok(raw) => if write(Self::path(app_name), raw).is_err() {
+                warn!("Failed to write downloads file!");
+            }
+        }
+    }
+
+    pub fn find(&mut self, dest: &str) -> Option<&mut DownloadRecord> {
+        self.records.iter_mut().find(|rec| rec.dest == dest)
+    }
+
+    fn path(app_name: &str) -> PathBuf {
+        let mut path = confy::get_configuration_file_path(app_name, None)
+            .unwrap_or_else(|_| PathBuf::from("."));
+        path.pop();
+        path.push(DOWNLOADS_FILE);
+        path
+    }
+}
@@ -0,0 +1,111 @@
+/*
+ * Author: Dylan Turner
+ * Description: Resolves, caches, and lazily revalidates site favicons
+ */
+
+use std::{
+    collections::HashMap,
+    fs::{ create_dir_all, read_to_string, write },
+    path::PathBuf,
+    time::{ SystemTime, UNIX_EPOCH }
+};
+use serde::{ Serialize, Deserialize };
+use log::warn;
+
+const CACHE_DIR: &'static str = "favicons";
+const CACHE_INDEX: &'static str = "favicons.json";
+const STALE_AFTER_SECS: u64 = 7 * 24 * 60 * 60; // re-fetch weekly
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FaviconEntry {
+    pub path: String,
+    pub fetched_at: u64
+}
+
+// Keyed by host, stored next to the confy config like History/Downloads
+#[derive(Serialize, Deserialize, Default)]
+pub struct FaviconCache {
+    pub entries: HashMap<String, FaviconEntry>
+}
+
+impl FaviconCache {
+    pub fn load(app_name: &str) -> Self {
+        match read_to_string(Self::index_path(app_name)) {
+            Err(_) => FaviconCache::default(),
+            Ok(raw) => serde_json::from_str(&raw).unwrap_or_default()
+        }
+    }
+
+    pub fn save(&self, app_name: &str) {
+        if let Ok(raw) = serde_json::to_string_pretty(self) {
+            let _ = write(Self::index_path(app_name), raw);
+        }
+    }
+
+    // Path to a (possibly stale) cached icon, if we have ever fetched one
+    pub fn cached_path(&self, host: &str) -> Option<String> {
+        self.entries.get(host).map(|entry| entry.path.clone())
+    }
+
+    // Stale entries are still served immediately; this just flags them so
+    // the caller can kick off a background re-fetch
+    pub fn needs_refresh(&self, host: &str) -> bool {
+        match self.entries.get(host) {
+            None => true,
+            Some(entry) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH).unwrap().as_secs();
+                now.saturating_sub(entry.fetched_at) > STALE_AFTER_SECS
+            }
+        }
+    }
+
+    // Persists freshly downloaded icon bytes for `host`, returning the path
+    pub fn record(
+        &mut self, app_name: &str, host: &str, bytes: &[u8]
+    ) -> Option<String> {
+        let dir = Self::cache_dir(app_name);
+        if create_dir_all(&dir).is_err() {
+            warn!("Failed to create favicon cache dir!");
+            return None;
+        }
+
+        let path = dir.join(format!("{}.ico", host));
+        if write(&path, bytes).is_err() {
+            warn!("Failed to write cached favicon for {}.", host);
+            return None;
+        }
+
+        let path_str = path.display().to_string();
+        self.entries.insert(host.to_string(), FaviconEntry {
+            path: path_str.clone(),
+            fetched_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH).unwrap().as_secs()
+        });
+        self.save(app_name);
+
+        Some(path_str)
+    }
+
+    fn cache_dir(app_name: &str) -> PathBuf {
+        let mut path = confy::get_configuration_file_path(app_name, None)
+            .unwrap_or_else(|_| PathBuf::from("."));
+        path.pop();
+        path.push(CACHE_DIR);
+        path
+    }
+
+    fn index_path(app_name: &str) -> PathBuf {
+        let mut path = Self::cache_dir(app_name);
+        path.pop();
+        path.push(CACHE_INDEX);
+        path
+    }
+}
+
+// Pulls the host out of a URL without pulling in a full URL-parsing crate
+pub fn host_of(url: &str) -> Option<String> {
+    let after_scheme = url.splitn(2, "://").nth(1)?;
+    let host = after_scheme.split('/').next()?;
+    if host.is_empty() { None } else { Some(host.to_string()) }
+}
@@ -0,0 +1,79 @@
+/*
+ * Author: Dylan Turner
+ * Description: Persistent, searchable browsing history
+ */
+
+use std::{
+    fs::{ read_to_string, write },
+    path::PathBuf,
+    time::{ SystemTime, UNIX_EPOCH }
+};
+use serde::{ Serialize, Deserialize };
+use log::warn;
+
+const HISTORY_FILE: &'static str = "history.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub url: String,
+    pub title: String,
+    pub visit_time: u64
+}
+
+// Stored as its own file next to the confy config, since confy itself
+// only speaks one typed config struct per app
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>
+}
+
+impl History {
+    pub fn load(app_name: &str) -> Self {
+        match read_to_string(Self::path(app_name)) {
+            Err(_) => History::default(),
+            Ok(raw) => match serde_json::from_str(&raw) {
+                Err(_) => {
+                    warn!("Error in history file! Starting empty.");
+                    History::default()
+                }, Ok(history) => history
+            }
+        }
+    }
+
+    pub fn save(&self, app_name: &str) {
+        match serde_json::to_string_pretty(self) {
+            Err(_) => warn!("Failed to serialize history!"),
+            Ok(raw) => if write(Self::path(app_name), raw).is_err() {
+                warn!("Failed to write history file!");
+            }
+        }
+    }
+
+    // Appends a visit and re-persists the whole history
+    pub fn record(&mut self, app_name: &str, url: String, title: String) {
+        self.entries.push(HistoryEntry {
+            url, title,
+            visit_time: SystemTime::now()
+                .duration_since(UNIX_EPOCH).unwrap().as_secs()
+        });
+        self.save(app_name);
+    }
+
+    // Substring match against both url and title, most recent first
+    pub fn search(&self, query: &str) -> Vec<&HistoryEntry> {
+        let needle = query.to_lowercase();
+        self.entries.iter().rev()
+            .filter(|entry| needle.is_empty()
+                || entry.url.to_lowercase().contains(&needle)
+                || entry.title.to_lowercase().contains(&needle))
+            .collect()
+    }
+
+    fn path(app_name: &str) -> PathBuf {
+        let mut path = confy::get_configuration_file_path(app_name, None)
+            .unwrap_or_else(|_| PathBuf::from("."));
+        path.pop();
+        path.push(HISTORY_FILE);
+        path
+    }
+}